@@ -0,0 +1,85 @@
+use futures_util::stream::{self, BoxStream, StreamExt};
+use hyperlane_core::ChainCommunicationError;
+use tendermint_rpc::event::{Event, EventData};
+use tendermint_rpc::query::EventType;
+use tendermint_rpc::{SubscriptionClient, WebSocketClient};
+use tokio::time::{interval, Duration};
+
+use crate::trait_builder::ConnectionConf;
+
+/// A newly produced block, as observed either from a `NewBlock` websocket
+/// event or from polling the latest height.
+#[derive(Debug, Clone)]
+pub struct NewBlock {
+    /// The height of the new block.
+    pub height: u64,
+}
+
+/// Default interval to poll the latest height at when no `websocket_url` is
+/// configured.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Subscribe to new blocks for the chain described by `conf`.
+///
+/// When a `websocket_url` is configured, this opens a Tendermint websocket
+/// connection and subscribes to `tm.event='NewBlock'`. Otherwise it falls
+/// back to polling the latest height over the configured RPC urls.
+pub async fn subscribe_new_blocks(
+    conf: ConnectionConf,
+) -> Result<BoxStream<'static, NewBlock>, ChainCommunicationError> {
+    match conf.get_websocket_url() {
+        Some(url) => websocket_blocks(url).await,
+        None => Ok(poll_blocks(conf)),
+    }
+}
+
+async fn websocket_blocks(
+    url: String,
+) -> Result<BoxStream<'static, NewBlock>, ChainCommunicationError> {
+    let (client, driver) = WebSocketClient::new(url.as_str())
+        .await
+        .map_err(|e| ChainCommunicationError::CustomError(e.to_string()))?;
+    tokio::spawn(driver.run());
+
+    let subscription = client
+        .subscribe(EventType::NewBlock.into())
+        .await
+        .map_err(|e| ChainCommunicationError::CustomError(e.to_string()))?;
+
+    let stream = subscription.filter_map(|res| async move {
+        let event: Event = res.ok()?;
+        let block = match &event.data {
+            EventData::NewBlock { block, .. } => block.as_ref(),
+            EventData::LegacyNewBlock { block, .. } => block.as_ref(),
+            _ => None,
+        }?;
+        Some(NewBlock {
+            height: block.header.height.value(),
+        })
+    });
+    Ok(stream.boxed())
+}
+
+fn poll_blocks(conf: ConnectionConf) -> BoxStream<'static, NewBlock> {
+    stream::unfold(
+        (conf, interval(POLL_INTERVAL), 0u64),
+        move |(conf, mut tick, last_height)| async move {
+            loop {
+                tick.tick().await;
+                let selector = conf.rpc_endpoint_selector();
+                let Ok(height) = selector
+                    .call_with_failover("poll_block_height", |url| async move {
+                        crate::query::latest_height(&url).await
+                    })
+                    .await
+                else {
+                    continue;
+                };
+                if height > last_height {
+                    return Some((NewBlock { height }, (conf, tick, height)));
+                }
+            }
+        },
+    )
+    .boxed()
+}
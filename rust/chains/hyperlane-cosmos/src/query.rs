@@ -0,0 +1,73 @@
+use hyperlane_core::ChainCommunicationError;
+use tendermint_rpc::{Client, HttpClient};
+
+use crate::trait_builder::{ConnectionConf, QueryTransport};
+
+/// Sends a state query as a Tendermint `abci_query` call over RPC, the
+/// fallback transport used when the `ConnectionConf` has no gRPC endpoint.
+/// When a gRPC endpoint is configured this returns an error without sending
+/// anything; gRPC queries go through the generated query clients instead.
+///
+/// `path` is the ABCI query path (e.g. `/cosmos.bank.v1beta1.Query/Balance`)
+/// and `data` is the protobuf-encoded request. The raw `value` bytes of the
+/// ABCI response are returned for the caller to decode with the matching
+/// response protobuf.
+pub async fn abci_query(
+    conf: &ConnectionConf,
+    path: String,
+    data: Vec<u8>,
+) -> Result<Vec<u8>, ChainCommunicationError> {
+    match conf.query_transport().map_err(|e| {
+        ChainCommunicationError::CustomError(e.to_string())
+    })? {
+        QueryTransport::Grpc(_) => {
+            // The gRPC path is handled by the generated query clients directly;
+            // this helper only exists to serve the RPC fallback path.
+            Err(ChainCommunicationError::CustomError(
+                "abci_query called while a gRPC transport is configured".to_owned(),
+            ))
+        }
+        QueryTransport::AbciQuery(selector) => selector
+            .call_with_failover("abci_query", |url| {
+                let path = path.clone();
+                let data = data.clone();
+                async move { abci_query_once(&url, path, data).await }
+            })
+            .await
+            .map_err(|e| ChainCommunicationError::CustomError(e.to_string())),
+    }
+}
+
+/// Fetch the latest committed block height from a single RPC endpoint.
+///
+/// Used by the polling fallback when no `websocket_url` is configured for
+/// block subscriptions.
+pub async fn latest_height(rpc_url: &str) -> Result<u64, ChainCommunicationError> {
+    let client = HttpClient::new(rpc_url)
+        .map_err(|e| ChainCommunicationError::CustomError(e.to_string()))?;
+    let status = client
+        .status()
+        .await
+        .map_err(|e| ChainCommunicationError::CustomError(e.to_string()))?;
+    Ok(status.sync_info.latest_block_height.value())
+}
+
+async fn abci_query_once(
+    rpc_url: &str,
+    path: String,
+    data: Vec<u8>,
+) -> Result<Vec<u8>, ChainCommunicationError> {
+    let client = HttpClient::new(rpc_url)
+        .map_err(|e| ChainCommunicationError::CustomError(e.to_string()))?;
+    let res = client
+        .abci_query(Some(path), data, None, false)
+        .await
+        .map_err(|e| ChainCommunicationError::CustomError(e.to_string()))?;
+    if res.code.is_err() {
+        return Err(ChainCommunicationError::CustomError(format!(
+            "abci_query failed with code {:?}: {}",
+            res.code, res.log
+        )));
+    }
+    Ok(res.value)
+}
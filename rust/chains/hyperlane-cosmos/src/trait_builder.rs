@@ -1,4 +1,6 @@
 use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
 use derive_new::new;
 use hyperlane_core::{ChainCommunicationError, FixedPointNumber};
@@ -6,10 +8,14 @@ use hyperlane_core::{ChainCommunicationError, FixedPointNumber};
 /// Cosmos connection configuration
 #[derive(Debug, Clone)]
 pub struct ConnectionConf {
-    /// The GRPC url to connect to
-    grpc_url: String,
-    /// The RPC url to connect to
-    rpc_url: String,
+    /// The GRPC urls to connect to, in priority order
+    grpc_urls: Vec<String>,
+    /// The RPC urls to connect to, in priority order
+    rpc_urls: Vec<String>,
+    /// The websocket url to subscribe to new block events on. Not every node
+    /// operator exposes this, so when absent the subsystem falls back to
+    /// polling the latest height over `rpc_urls`.
+    websocket_url: Option<String>,
     /// The chain ID
     chain_id: String,
     /// The human readable address prefix for the chains using bech32.
@@ -24,6 +30,13 @@ pub struct ConnectionConf {
     /// Cosmos address lengths are sometimes less than 32 bytes, so this helps to serialize it in
     /// bech32 with the appropriate length.
     contract_address_bytes: usize,
+    /// A single, shared round-robin/failover selector over `grpc_urls`, built
+    /// once so its cursor actually accumulates state across requests instead
+    /// of restarting at the primary url every time one is handed out.
+    grpc_selector: Arc<EndpointSelector>,
+    /// A single, shared round-robin/failover selector over `rpc_urls`, for
+    /// the same reason as `grpc_selector`.
+    rpc_selector: Arc<EndpointSelector>,
 }
 
 /// Untyped cosmos amount
@@ -72,17 +85,155 @@ pub enum ConnectionConfError {
     /// Invalid `url` for connection configuration
     #[error("Invalid `url` for connection configuration: `{0}` ({1})")]
     InvalidConnectionUrl(String, url::ParseError),
+    /// No healthy endpoints remain for this connection configuration
+    #[error("No healthy endpoints remain for `{0}` connection configuration")]
+    NoHealthyEndpoints(&'static str),
+    /// Neither a gRPC nor an RPC endpoint is configured, so state queries have
+    /// no transport to go over.
+    #[error("No gRPC or RPC endpoint configured to serve queries")]
+    MissingQueryTransport,
+}
+
+/// The transport a state query should be sent over.
+#[derive(Debug, Clone)]
+pub enum QueryTransport {
+    /// Send the query as a gRPC request against one of the configured gRPC urls.
+    Grpc(Arc<EndpointSelector>),
+    /// No gRPC endpoint is configured; fall back to a Tendermint `abci_query`
+    /// call against one of the configured RPC urls.
+    AbciQuery(Arc<EndpointSelector>),
+}
+
+/// A round-robin/failover selector over an ordered set of endpoint urls.
+///
+/// Mirrors the approach the Ethereum `PrometheusJsonRpcClient` relies on when
+/// wrapped by a quorum provider: callers ask for the next candidate, try it,
+/// and on failure move on to the following url. Failures are not remembered
+/// across calls, so a failed url stays in the rotation.
+#[derive(Debug)]
+pub struct EndpointSelector {
+    urls: Vec<String>,
+    cursor: AtomicUsize,
+}
+
+impl Clone for EndpointSelector {
+    fn clone(&self) -> Self {
+        Self {
+            urls: self.urls.clone(),
+            cursor: AtomicUsize::new(self.cursor.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl EndpointSelector {
+    /// Create a new selector over the given urls, in priority order.
+    pub fn new(urls: Vec<String>) -> Self {
+        Self {
+            urls,
+            cursor: AtomicUsize::new(0),
+        }
+    }
+
+    /// The primary (first configured) url, if any.
+    pub fn primary(&self) -> Option<&String> {
+        self.urls.first()
+    }
+
+    /// All configured urls, in priority order.
+    pub fn urls(&self) -> &[String] {
+        &self.urls
+    }
+
+    /// Return the next url to try, round-robining across the configured set.
+    pub fn next(&self) -> Option<&String> {
+        if self.urls.is_empty() {
+            return None;
+        }
+        let idx = self.cursor.fetch_add(1, Ordering::Relaxed) % self.urls.len();
+        self.urls.get(idx)
+    }
+
+    /// Run `f` against each endpoint in turn, starting from the next
+    /// round-robin position, returning the first success. Callers should
+    /// report transport failures by simply letting `f` return `Err` so the
+    /// selector moves on to the next candidate.
+    pub async fn call_with_failover<T, E, F, Fut>(
+        &self,
+        label: &'static str,
+        mut f: F,
+    ) -> Result<T, ConnectionConfError>
+    where
+        F: FnMut(String) -> Fut,
+        Fut: std::future::Future<Output = Result<T, E>>,
+    {
+        if self.urls.is_empty() {
+            return Err(ConnectionConfError::NoHealthyEndpoints(label));
+        }
+        let start = self.cursor.fetch_add(1, Ordering::Relaxed) % self.urls.len();
+        for offset in 0..self.urls.len() {
+            let url = &self.urls[(start + offset) % self.urls.len()];
+            if let Ok(res) = f(url.clone()).await {
+                return Ok(res);
+            }
+        }
+        Err(ConnectionConfError::NoHealthyEndpoints(label))
+    }
 }
 
 impl ConnectionConf {
-    /// Get the GRPC url
-    pub fn get_grpc_url(&self) -> String {
-        self.grpc_url.clone()
+    /// Get the GRPC urls, in priority order
+    pub fn get_grpc_urls(&self) -> Vec<String> {
+        self.grpc_urls.clone()
+    }
+
+    /// Get the primary GRPC url, if one is configured. Not every node operator
+    /// exposes gRPC, so this is optional.
+    pub fn get_grpc_url(&self) -> Option<String> {
+        self.grpc_urls.first().cloned()
+    }
+
+    /// A round-robin/failover selector over the configured GRPC urls. Always
+    /// returns a clone of the same shared selector, so its cursor keeps
+    /// rotating across calls instead of restarting at the primary url.
+    pub fn grpc_endpoint_selector(&self) -> Arc<EndpointSelector> {
+        self.grpc_selector.clone()
+    }
+
+    /// The transport state queries should be sent over: gRPC when configured,
+    /// otherwise Tendermint `abci_query` over the RPC endpoint.
+    pub fn query_transport(&self) -> Result<QueryTransport, ConnectionConfError> {
+        if !self.grpc_urls.is_empty() {
+            return Ok(QueryTransport::Grpc(self.grpc_endpoint_selector()));
+        }
+        if !self.rpc_urls.is_empty() {
+            return Ok(QueryTransport::AbciQuery(self.rpc_endpoint_selector()));
+        }
+        Err(ConnectionConfError::MissingQueryTransport)
     }
 
-    /// Get the RPC url
+    /// Get the RPC urls, in priority order
+    pub fn get_rpc_urls(&self) -> Vec<String> {
+        self.rpc_urls.clone()
+    }
+
+    /// Get the primary RPC url, kept for backward compatibility
     pub fn get_rpc_url(&self) -> String {
-        self.rpc_url.clone()
+        self.rpc_urls
+            .first()
+            .cloned()
+            .expect("ConnectionConf::new enforces at least one rpc_url")
+    }
+
+    /// A round-robin/failover selector over the configured RPC urls. Always
+    /// returns a clone of the same shared selector, so its cursor keeps
+    /// rotating across calls instead of restarting at the primary url.
+    pub fn rpc_endpoint_selector(&self) -> Arc<EndpointSelector> {
+        self.rpc_selector.clone()
+    }
+
+    /// Get the websocket url to subscribe to new block events on, if configured
+    pub fn get_websocket_url(&self) -> Option<String> {
+        self.websocket_url.clone()
     }
 
     /// Get the chain ID
@@ -110,24 +261,36 @@ impl ConnectionConf {
         self.contract_address_bytes
     }
 
-    /// Create a new connection configuration
+    /// Create a new connection configuration.
+    ///
+    /// `rpc_urls` is mandatory: at least one RPC endpoint must be configured,
+    /// since `get_rpc_url` and the RPC fallback transport both depend on it.
     pub fn new(
-        grpc_url: String,
-        rpc_url: String,
+        grpc_urls: Vec<String>,
+        rpc_urls: Vec<String>,
+        websocket_url: Option<String>,
         chain_id: String,
         bech32_prefix: String,
         canonical_asset: String,
         minimum_gas_price: RawCosmosAmount,
         contract_address_bytes: usize,
-    ) -> Self {
-        Self {
-            grpc_url,
-            rpc_url,
+    ) -> Result<Self, ConnectionConfError> {
+        if rpc_urls.is_empty() {
+            return Err(ConnectionConfError::MissingConnectionRpcUrl);
+        }
+        let grpc_selector = Arc::new(EndpointSelector::new(grpc_urls.clone()));
+        let rpc_selector = Arc::new(EndpointSelector::new(rpc_urls.clone()));
+        Ok(Self {
+            grpc_urls,
+            rpc_urls,
+            websocket_url,
             chain_id,
             bech32_prefix,
             canonical_asset,
             gas_price: minimum_gas_price,
             contract_address_bytes,
-        }
+            grpc_selector,
+            rpc_selector,
+        })
     }
 }
@@ -1,19 +1,26 @@
-//! A wrapper around a JsonRpcClient to give insight at the request level. This
-//! was designed specifically for use with the quorum provider.
+//! Wrappers around a JsonRpcClient. `PrometheusJsonRpcClient` gives insight at
+//! the request level, and `FallbackJsonRpcClient` turns a set of inner
+//! clients into an adaptive, health-scoring router rather than relying on
+//! caller-side (e.g. quorum provider) selection.
 
+use std::collections::VecDeque;
 use std::fmt::{Debug, Formatter};
-use std::time::Instant;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
 use derive_builder::Builder;
 use derive_new::new;
 use ethers::prelude::JsonRpcClient;
 use ethers_core::types::U64;
+use ethers_core::types::U256;
 use hyperlane_core::rpc_clients::BlockNumberGetter;
 use hyperlane_core::ChainCommunicationError;
 use maplit::hashmap;
-use prometheus::{CounterVec, IntCounterVec};
-use serde::{de::DeserializeOwned, Serialize};
+use prometheus::{CounterVec, HistogramVec, IntCounterVec, IntGaugeVec};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::time::interval;
 
 pub use crate::ChainInfo;
 
@@ -51,6 +58,36 @@ pub struct JsonRpcClientMetrics {
     ///   might still be an "error" but not one with the transport layer.
     #[builder(setter(into, strip_option), default)]
     request_duration_seconds: Option<CounterVec>,
+
+    /// Histogram of the number of seconds spent making requests, bucketed for
+    /// computing percentiles (p50/p95/p99) per method per node. This is the
+    /// recommended metric for comparing endpoint tail latency; the counter
+    /// above is kept only for backward compatibility.
+    /// - `provider_node`: node this is connecting to, e.g. `alchemy.com`,
+    ///   `quicknode.pro`, or `localhost:8545`.
+    /// - `chain`: chain name (or chain id if the name is unknown) of the chain
+    ///   the request was made on.
+    /// - `method`: request method string.
+    /// - `status`: `success` or `failure` depending on the response. A `success`
+    ///   might still be an "error" but not one with the transport layer.
+    #[builder(setter(into, strip_option), default)]
+    request_duration_histogram: Option<HistogramVec>,
+
+    /// Number of requests currently in flight.
+    /// - `provider_node`: node this is connecting to, e.g. `alchemy.com`,
+    ///   `quicknode.pro`, or `localhost:8545`.
+    /// - `chain`: chain name (or chain id if the name is unknown) of the chain
+    ///   the request was made on.
+    /// - `method`: request method string.
+    #[builder(setter(into, strip_option), default)]
+    in_flight_requests: Option<IntGaugeVec>,
+
+    /// Total number of times a node was selected to serve a request by
+    /// `FallbackJsonRpcClient`.
+    /// - `provider_node`: node this is connecting to, e.g. `alchemy.com`,
+    ///   `quicknode.pro`, or `localhost:8545`.
+    #[builder(setter(into, strip_option), default)]
+    provider_selected: Option<IntCounterVec>,
 }
 
 /// Expected label names for the metric.
@@ -64,6 +101,27 @@ pub const REQUEST_DURATION_SECONDS_LABELS: &[&str] =
 /// Help string for the metric.
 pub const REQUEST_DURATION_SECONDS_HELP: &str = "Total number of seconds spent making requests";
 
+/// Expected label names for the metric.
+pub const REQUEST_DURATION_HISTOGRAM_LABELS: &[&str] =
+    &["provider_node", "chain", "method", "status"];
+/// Help string for the metric.
+pub const REQUEST_DURATION_HISTOGRAM_HELP: &str =
+    "Histogram of seconds spent making requests, bucketed for percentile calculations";
+/// Default bucket boundaries (in seconds) for `request_duration_histogram`.
+pub const REQUEST_DURATION_HISTOGRAM_BUCKETS: &[f64] =
+    &[0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Expected label names for the metric.
+pub const IN_FLIGHT_REQUESTS_LABELS: &[&str] = &["provider_node", "chain", "method"];
+/// Help string for the metric.
+pub const IN_FLIGHT_REQUESTS_HELP: &str = "Number of requests currently in flight";
+
+/// Expected label names for the metric.
+pub const PROVIDER_SELECTED_LABELS: &[&str] = &["provider_node"];
+/// Help string for the metric.
+pub const PROVIDER_SELECTED_HELP: &str =
+    "Total number of times a node was selected to serve a request by the fallback router";
+
 /// Configuration for the prometheus JsonRpcClioent. This can be loaded via
 /// serde.
 #[derive(Default, Clone, Debug)]
@@ -140,6 +198,20 @@ impl<C> PrometheusJsonRpcClient<C> {
     }
 }
 
+/// RAII guard that decrements an in-flight-requests gauge on drop, so the
+/// count is released even if the request future is cancelled (e.g. wrapped
+/// in `tokio::time::timeout`) before it resolves.
+struct InFlightGuard<'a> {
+    gauge: &'a IntGaugeVec,
+    labels: std::collections::HashMap<&'a str, &'a str>,
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.gauge.with(&self.labels).dec();
+    }
+}
+
 impl<C> PrometheusJsonRpcClientConfigExt for PrometheusJsonRpcClient<C> {
     /// The "host" part of the URL this node is connecting to. E.g.
     /// `avalanche.api.onfinality.io`.
@@ -166,8 +238,25 @@ where
         T: Debug + Serialize + Send + Sync,
         R: DeserializeOwned,
     {
+        let in_flight_labels = hashmap! {
+            "provider_node" => self.config.node_host(),
+            "chain" => self.config.chain_name(),
+            "method" => method,
+        };
+        let _in_flight_guard = self.metrics.in_flight_requests.as_ref().map(|gauge| {
+            gauge.with(&in_flight_labels).inc();
+            InFlightGuard {
+                gauge,
+                labels: in_flight_labels.clone(),
+            }
+        });
+
         let start = Instant::now();
         let res = self.inner.request(method, params).await;
+        let duration = (Instant::now() - start).as_secs_f64();
+
+        drop(_in_flight_guard);
+
         let labels = hashmap! {
             "provider_node" => self.config.node_host(),
             "chain" => self.config.chain_name(),
@@ -178,9 +267,10 @@ where
             counter.with(&labels).inc()
         }
         if let Some(counter) = &self.metrics.request_duration_seconds {
-            counter
-                .with(&labels)
-                .inc_by((Instant::now() - start).as_secs_f64())
+            counter.with(&labels).inc_by(duration)
+        };
+        if let Some(histogram) = &self.metrics.request_duration_histogram {
+            histogram.with(&labels).observe(duration)
         };
         res
     }
@@ -214,3 +304,446 @@ where
         Ok(res)
     }
 }
+
+/// RPC method for getting historical base fee and priority fee information.
+pub const FEE_HISTORY_RPC: &str = "eth_feeHistory";
+
+/// The decoded result of an `eth_feeHistory` call.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeHistory {
+    /// Base fee per gas for each block in the range, plus the base fee for
+    /// the next block after the range (length == `block_count + 1`).
+    pub base_fee_per_gas: Vec<U256>,
+    /// Ratio of gas used to gas limit for each block in the range, each in
+    /// `[0, 1]`.
+    pub gas_used_ratio: Vec<f64>,
+    /// Reward amounts for each of the requested `reward_percentiles`, for
+    /// each block in the range. Absent if no percentiles were requested.
+    #[serde(default)]
+    pub reward: Vec<Vec<U256>>,
+}
+
+/// Trait for retrieving `eth_feeHistory` data from a JSON-RPC client, for use
+/// in computing priority-fee estimates for gas-aware submission.
+#[async_trait]
+pub trait FeeHistoryGetter: Send + Sync {
+    /// Fetch fee history for the `block_count` blocks ending at `newest_block`,
+    /// along with the given `reward_percentiles`.
+    async fn get_fee_history(
+        &self,
+        block_count: u64,
+        newest_block: &str,
+        reward_percentiles: &[f64],
+    ) -> Result<FeeHistory, ChainCommunicationError>;
+}
+
+/// Utility struct for implementing `FeeHistoryGetter`
+#[derive(Debug, new)]
+pub struct JsonRpcFeeHistoryGetter<T: JsonRpcClient>(T);
+
+#[async_trait]
+impl<C> FeeHistoryGetter for JsonRpcFeeHistoryGetter<C>
+where
+    C: JsonRpcClient,
+{
+    async fn get_fee_history(
+        &self,
+        block_count: u64,
+        newest_block: &str,
+        reward_percentiles: &[f64],
+    ) -> Result<FeeHistory, ChainCommunicationError> {
+        let params = (U64::from(block_count), newest_block, reward_percentiles);
+        let fee_history: FeeHistory = self
+            .0
+            .request(FEE_HISTORY_RPC, params)
+            .await
+            .map_err(Into::into)?;
+
+        if fee_history.base_fee_per_gas.len() as u64 != block_count + 1 {
+            return Err(ChainCommunicationError::CustomError(format!(
+                "eth_feeHistory returned {} baseFeePerGas entries, expected {}",
+                fee_history.base_fee_per_gas.len(),
+                block_count + 1
+            )));
+        }
+
+        if let Some(bad_ratio) = fee_history
+            .gas_used_ratio
+            .iter()
+            .find(|&&ratio| !(0.0..=1.0).contains(&ratio))
+        {
+            return Err(ChainCommunicationError::CustomError(format!(
+                "eth_feeHistory returned an out-of-range gasUsedRatio: {bad_ratio}"
+            )));
+        }
+
+        Ok(fee_history)
+    }
+}
+
+/// The EWMA smoothing factor applied to each new latency sample.
+const LATENCY_EWMA_ALPHA: f64 = 0.2;
+/// Number of recent outcomes kept in each node's sliding error-rate window.
+const ERROR_WINDOW_SIZE: usize = 20;
+/// A node is treated as unhealthy once its error rate within the window
+/// exceeds this fraction, in addition to being unhealthy while degraded.
+const ERROR_RATE_DEGRADED_THRESHOLD: f64 = 0.5;
+/// Initial backoff applied the first time a node errors.
+const BASE_BACKOFF: Duration = Duration::from_millis(250);
+/// Ceiling on the exponential backoff applied to a repeatedly erroring node.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// How often the background task polls `eth_blockNumber` on every node.
+const DEFAULT_HEIGHT_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Rolling per-node health signal used by [`FallbackJsonRpcClient`] to route
+/// requests away from lagging or erroring nodes.
+#[derive(Debug)]
+struct NodeHealth {
+    /// Exponentially weighted moving average of request latency, in seconds.
+    latency_ewma_secs: Mutex<f64>,
+    /// Sliding window of the most recent request outcomes (`true` = error).
+    error_window: Mutex<VecDeque<bool>>,
+    /// Most recent block height observed from this node by the background
+    /// refresh task. Ordinary requests do not update it.
+    last_height: AtomicU64,
+    /// Set while the node is temporarily degraded after a transport error;
+    /// cleared on the next success.
+    degraded_until: Mutex<Option<Instant>>,
+    /// Backoff applied the next time this node errors; doubles on repeated
+    /// errors (up to [`MAX_BACKOFF`]) and resets to [`BASE_BACKOFF`] on success.
+    backoff: Mutex<Duration>,
+}
+
+impl Default for NodeHealth {
+    fn default() -> Self {
+        Self {
+            latency_ewma_secs: Mutex::new(0.0),
+            error_window: Mutex::new(VecDeque::with_capacity(ERROR_WINDOW_SIZE)),
+            last_height: AtomicU64::new(0),
+            degraded_until: Mutex::new(None),
+            backoff: Mutex::new(BASE_BACKOFF),
+        }
+    }
+}
+
+impl NodeHealth {
+    fn ewma_latency_secs(&self) -> f64 {
+        *self.latency_ewma_secs.lock().unwrap()
+    }
+
+    fn is_degraded(&self) -> bool {
+        matches!(*self.degraded_until.lock().unwrap(), Some(until) if Instant::now() < until)
+    }
+
+    fn error_rate(&self) -> f64 {
+        let window = self.error_window.lock().unwrap();
+        if window.is_empty() {
+            return 0.0;
+        }
+        window.iter().filter(|&&errored| errored).count() as f64 / window.len() as f64
+    }
+
+    fn record_outcome(&self, errored: bool) {
+        let mut window = self.error_window.lock().unwrap();
+        if window.len() == ERROR_WINDOW_SIZE {
+            window.pop_front();
+        }
+        window.push_back(errored);
+    }
+
+    fn record_success(&self, latency: Duration) {
+        let mut ewma = self.latency_ewma_secs.lock().unwrap();
+        *ewma = LATENCY_EWMA_ALPHA * latency.as_secs_f64() + (1.0 - LATENCY_EWMA_ALPHA) * *ewma;
+        drop(ewma);
+
+        self.record_outcome(false);
+        *self.degraded_until.lock().unwrap() = None;
+        *self.backoff.lock().unwrap() = BASE_BACKOFF;
+    }
+
+    fn record_error(&self) {
+        self.record_outcome(true);
+
+        let mut backoff = self.backoff.lock().unwrap();
+        *self.degraded_until.lock().unwrap() = Some(Instant::now() + *backoff);
+        *backoff = (*backoff * 2).min(MAX_BACKOFF);
+    }
+
+    fn observe_height(&self, height: u64) {
+        self.last_height.fetch_max(height, Ordering::Relaxed);
+    }
+
+    /// Sort key for candidate selection: unhealthy nodes (degraded or with a
+    /// high recent error rate) sort after healthy ones, and within each group
+    /// nodes lagging behind `max_height` by more than `max_block_lag` sort
+    /// last. Ties are broken by the caller on EWMA latency.
+    fn rank(&self, max_height: u64, max_block_lag: u64) -> (u8, u8) {
+        let unhealthy =
+            (self.is_degraded() || self.error_rate() > ERROR_RATE_DEGRADED_THRESHOLD) as u8;
+        let lag = max_height.saturating_sub(self.last_height.load(Ordering::Relaxed));
+        let lagging = (lag > max_block_lag) as u8;
+        (unhealthy, lagging)
+    }
+}
+
+struct FallbackNode<C> {
+    client: C,
+    health: NodeHealth,
+}
+
+/// A [`JsonRpcClient`] that wraps an ordered set of inner clients (typically
+/// [`PrometheusJsonRpcClient`]s) and routes each request to whichever node
+/// looks healthiest, rather than relying on caller-side selection.
+///
+/// Each node's rolling health is tracked as an EWMA of request latency, a
+/// sliding error-rate window, and the last block height observed by a
+/// periodic background poll of [`BLOCK_NUMBER_RPC`].
+/// Candidates are preferred if their height is within `max_block_lag` of the
+/// tallest observed height, breaking ties by lowest EWMA latency; a node that
+/// errors is marked degraded under exponential backoff and the next
+/// candidate is tried instead.
+pub struct FallbackJsonRpcClient<C> {
+    nodes: Arc<Vec<FallbackNode<C>>>,
+    max_block_lag: u64,
+    metrics: JsonRpcClientMetrics,
+}
+
+impl<C> Debug for FallbackJsonRpcClient<C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "FallbackJsonRpcClient({} nodes)", self.nodes.len())
+    }
+}
+
+impl<C> Clone for FallbackJsonRpcClient<C> {
+    fn clone(&self) -> Self {
+        Self {
+            nodes: self.nodes.clone(),
+            max_block_lag: self.max_block_lag,
+            metrics: self.metrics.clone(),
+        }
+    }
+}
+
+impl<C> FallbackJsonRpcClient<C>
+where
+    C: JsonRpcClient + Clone + PrometheusJsonRpcClientConfigExt + Send + Sync + 'static,
+{
+    /// Build a fallback client over `clients`, in priority order. Spawns a
+    /// background task that polls [`BLOCK_NUMBER_RPC`] on every node every
+    /// [`DEFAULT_HEIGHT_REFRESH_INTERVAL`]; this poll is the only thing that
+    /// updates a node's observed height, so lag detection works whichever
+    /// methods the caller is requesting.
+    ///
+    /// Returns an error if `clients` is empty: every candidate-selection and
+    /// request path below assumes there's at least one node to route to.
+    pub fn new(
+        clients: Vec<C>,
+        max_block_lag: u64,
+        metrics: JsonRpcClientMetrics,
+    ) -> Result<Self, ChainCommunicationError> {
+        if clients.is_empty() {
+            return Err(ChainCommunicationError::CustomError(
+                "FallbackJsonRpcClient must wrap at least one inner client".to_owned(),
+            ));
+        }
+
+        let nodes = Arc::new(
+            clients
+                .into_iter()
+                .map(|client| FallbackNode {
+                    client,
+                    health: NodeHealth::default(),
+                })
+                .collect::<Vec<_>>(),
+        );
+
+        let refresh_nodes = nodes.clone();
+        tokio::spawn(async move {
+            let mut tick = interval(DEFAULT_HEIGHT_REFRESH_INTERVAL);
+            loop {
+                tick.tick().await;
+                refresh_heights(&refresh_nodes).await;
+            }
+        });
+
+        Ok(Self {
+            nodes,
+            max_block_lag,
+            metrics,
+        })
+    }
+
+    /// Indices of the wrapped nodes, ordered from most to least preferred for
+    /// the next request.
+    fn candidate_order(&self) -> Vec<usize> {
+        let max_height = self
+            .nodes
+            .iter()
+            .map(|node| node.health.last_height.load(Ordering::Relaxed))
+            .max()
+            .unwrap_or(0);
+
+        let mut indices: Vec<usize> = (0..self.nodes.len()).collect();
+        indices.sort_by(|&a, &b| {
+            let rank_a = self.nodes[a].health.rank(max_height, self.max_block_lag);
+            let rank_b = self.nodes[b].health.rank(max_height, self.max_block_lag);
+            rank_a.cmp(&rank_b).then_with(|| {
+                self.nodes[a]
+                    .health
+                    .ewma_latency_secs()
+                    .partial_cmp(&self.nodes[b].health.ewma_latency_secs())
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+        });
+        indices
+    }
+}
+
+async fn refresh_heights<C>(nodes: &[FallbackNode<C>])
+where
+    C: JsonRpcClient + Clone,
+{
+    for node in nodes {
+        let getter = JsonRpcBlockGetter::new(node.client.clone());
+        if let Ok(height) = getter.get_block_number().await {
+            node.health.observe_height(height);
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<C> JsonRpcClient for FallbackJsonRpcClient<C>
+where
+    C: JsonRpcClient + Clone + PrometheusJsonRpcClientConfigExt + Send + Sync + 'static,
+{
+    type Error = C::Error;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned,
+    {
+        let order = self.candidate_order();
+
+        // Serialize once so the same params can be retried against multiple
+        // candidates; `T` isn't required to be `Clone` by the trait we're
+        // implementing. If serialization fails, make a single direct attempt
+        // against the top candidate rather than silently dropping the error.
+        let json_params = match serde_json::to_value(&params) {
+            Ok(value) => value,
+            Err(_) => return self.nodes[order[0]].client.request(method, params).await,
+        };
+
+        let mut last_err = None;
+        for idx in order {
+            let node = &self.nodes[idx];
+            let start = Instant::now();
+            match node.client.request(method, json_params.clone()).await {
+                Ok(res) => {
+                    node.health.record_success(Instant::now() - start);
+                    if let Some(counter) = &self.metrics.provider_selected {
+                        counter
+                            .with(&hashmap! { "provider_node" => node.client.node_host() })
+                            .inc();
+                    }
+                    return Ok(res);
+                }
+                Err(err) => {
+                    node.health.record_error();
+                    last_err = Some(err);
+                }
+            }
+        }
+        Err(last_err.expect("FallbackJsonRpcClient must wrap at least one inner client"))
+    }
+}
+
+#[async_trait]
+impl<C> BlockNumberGetter for FallbackJsonRpcClient<C>
+where
+    C: JsonRpcClient + Clone + PrometheusJsonRpcClientConfigExt + Send + Sync + 'static,
+{
+    async fn get_block_number(&self) -> Result<u64, ChainCommunicationError> {
+        JsonRpcBlockGetter::new(self.clone())
+            .get_block_number()
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `rank()` returns the exact (unhealthy, lagging) tuple that
+    // `candidate_order` sorts nodes by, so comparing ranks directly exercises
+    // the same ordering without needing a full `FallbackJsonRpcClient<C>`.
+    fn ranks_in_order(
+        worse: &NodeHealth,
+        better: &NodeHealth,
+        max_height: u64,
+        max_block_lag: u64,
+    ) {
+        assert!(worse.rank(max_height, max_block_lag) > better.rank(max_height, max_block_lag));
+    }
+
+    #[test]
+    fn degraded_node_sorts_after_healthy() {
+        let degraded = NodeHealth::default();
+        degraded.record_error();
+        let healthy = NodeHealth::default();
+
+        ranks_in_order(&degraded, &healthy, 0, 0);
+    }
+
+    #[test]
+    fn lagging_node_sorts_last() {
+        let lagging = NodeHealth::default();
+        lagging.observe_height(90);
+        let caught_up = NodeHealth::default();
+        caught_up.observe_height(100);
+
+        ranks_in_order(&lagging, &caught_up, 100, 5);
+    }
+
+    #[test]
+    fn node_within_max_block_lag_is_not_lagging() {
+        // Within `max_block_lag` of the tallest height, a node is not
+        // considered lagging even if it's a little behind.
+        let node = NodeHealth::default();
+        node.observe_height(97);
+
+        assert_eq!(node.rank(100, 5), (0, 0));
+    }
+
+    #[test]
+    fn backoff_doubles_on_repeated_errors_up_to_max() {
+        let health = NodeHealth::default();
+        assert_eq!(*health.backoff.lock().unwrap(), BASE_BACKOFF);
+
+        health.record_error();
+        assert_eq!(*health.backoff.lock().unwrap(), BASE_BACKOFF * 2);
+
+        health.record_error();
+        assert_eq!(*health.backoff.lock().unwrap(), BASE_BACKOFF * 4);
+
+        for _ in 0..10 {
+            health.record_error();
+        }
+        assert_eq!(*health.backoff.lock().unwrap(), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn backoff_resets_and_degraded_clears_on_success() {
+        let health = NodeHealth::default();
+        health.record_error();
+        health.record_error();
+        assert!(health.is_degraded());
+        assert_eq!(*health.backoff.lock().unwrap(), BASE_BACKOFF * 4);
+
+        health.record_success(Duration::from_millis(10));
+        assert!(!health.is_degraded());
+        assert_eq!(*health.backoff.lock().unwrap(), BASE_BACKOFF);
+    }
+}